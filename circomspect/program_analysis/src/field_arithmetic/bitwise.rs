@@ -0,0 +1,194 @@
+//! Shift- and bitwise-width alarms for Circom's signed 254-bit field
+//! representation.
+//!
+//! `may_overflow` already notes that right-shift can "overflow if the shift
+//! is less than 0", but the generic overflow pass treats every shift
+//! identically and exempts `BitOr`/`BitAnd`/`BitXor` entirely. This pass
+//! models Circom's actual semantics, where field elements are interpreted
+//! as signed 254-bit values for bitwise and shift operators (analogous to
+//! Frama-C's dedicated alarm for shifting a negative value).
+
+use log::debug;
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+use program_structure::cfg::Cfg;
+use program_structure::report_code::ReportCode;
+use program_structure::report::{Report, ReportCollection};
+use program_structure::file_definition::{FileID, FileLocation};
+use program_structure::ir::*;
+
+use super::interval::ValueRanges;
+use super::visit::walk_cfg;
+
+/// The number of bits Circom uses when interpreting a field element as a
+/// signed integer for bitwise and shift operators.
+const FIELD_BIT_WIDTH: u32 = 254;
+
+pub struct ShiftAmountOutOfRangeWarning {
+    file_id: Option<FileID>,
+    file_location: FileLocation,
+    message: String,
+}
+
+impl ShiftAmountOutOfRangeWarning {
+    fn into_report(self) -> Report {
+        let mut report = Report::info(self.message, ReportCode::ShiftAmountOutOfRange);
+        if let Some(file_id) = self.file_id {
+            report.add_primary(self.file_location, file_id, "Shift here.".to_string());
+        }
+        report
+    }
+}
+
+pub struct SignedBitwiseMismatchWarning {
+    file_id: Option<FileID>,
+    file_location: FileLocation,
+}
+
+impl SignedBitwiseMismatchWarning {
+    fn into_report(self) -> Report {
+        let mut report = Report::info(
+            "This operand's value range extends into the upper half of the field, where Circom's \
+             signed two's-complement interpretation of field elements may not match the intended \
+             bitwise semantics."
+                .to_string(),
+            ReportCode::SignedBitwiseMismatch,
+        );
+        if let Some(file_id) = self.file_id {
+            report.add_primary(self.file_location, file_id, "Bitwise operation here.".to_string());
+        }
+        report
+    }
+}
+
+/// Find shift operations with an out-of-range (or non-constant) shift
+/// amount, and bitwise operations whose operand range diverges from
+/// Circom's signed field-element interpretation.
+///
+/// Constants are propagated through the CFG first (see
+/// `program_structure::sccp::propagate_constants`) for the same precision
+/// reasons as in `find_field_element_arithmetic`.
+pub fn find_shift_and_bitwise_issues(cfg: &mut Cfg) -> ReportCollection {
+    debug!("running shift and bitwise width analysis pass");
+    program_structure::sccp::propagate_constants(cfg);
+    let ranges = ValueRanges::compute(cfg, cfg.curve());
+    let mut reports = ReportCollection::new();
+    walk_cfg(cfg, |meta, infix_op, lhe, rhe| {
+        use ExpressionInfixOpcode::*;
+        match infix_op {
+            ShiftL | ShiftR => check_shift(meta, rhe, &ranges, &mut reports),
+            BitAnd | BitOr | BitXor => check_bitwise(meta, lhe, rhe, &ranges, &mut reports),
+            _ => (),
+        }
+        true
+    });
+    debug!("{} new reports generated", reports.len());
+    reports
+}
+
+fn check_shift(meta: &Meta, amount: &Expression, ranges: &ValueRanges, reports: &mut ReportCollection) {
+    let message = match ranges.as_constant(amount) {
+        Some(n) if n < BigInt::zero() => {
+            Some("Shift amount is negative, which may not produce the expected result.".to_string())
+        }
+        Some(n) if n >= BigInt::from(FIELD_BIT_WIDTH) => Some(format!(
+            "Shift amount is not smaller than the field's bit-width ({FIELD_BIT_WIDTH}), which may \
+             not produce the expected result."
+        )),
+        Some(_) => None,
+        None => Some(
+            "Shift amount is not a compile-time constant, so it cannot be checked against the \
+             field's bit-width."
+                .to_string(),
+        ),
+    };
+    if let Some(message) = message {
+        reports.push(
+            ShiftAmountOutOfRangeWarning {
+                file_id: meta.file_id(),
+                file_location: meta.file_location(),
+                message,
+            }
+            .into_report(),
+        );
+    }
+}
+
+fn check_bitwise(
+    meta: &Meta,
+    lhe: &Expression,
+    rhe: &Expression,
+    ranges: &ValueRanges,
+    reports: &mut ReportCollection,
+) {
+    // Once an operand's range reaches the upper half of the field, Circom's
+    // signed two's-complement interpretation of that value as a bitwise
+    // operand diverges from what a programmer reasoning about plain
+    // integers would expect. An operand that's simply unconstrained (the
+    // default `[0, p - 1]` given to signals, array accesses, and function
+    // parameters) is the normal case for bit-decomposition circuits, so
+    // only warn once the range analysis has actually established a
+    // narrower-than-default bound that still crosses the threshold.
+    let threshold = BigInt::from(1) << (FIELD_BIT_WIDTH - 1);
+    let exceeds = |expr| ranges.established_upper_bound(expr).is_some_and(|hi| hi >= threshold);
+    if exceeds(lhe) || exceeds(rhe) {
+        reports.push(
+            SignedBitwiseMismatchWarning {
+                file_id: meta.file_id(),
+                file_location: meta.file_location(),
+            }
+            .into_report(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::parse_definition;
+    use program_structure::{cfg::IntoCfg, constants::Curve};
+
+    use super::*;
+
+    #[test]
+    fn test_shift_amount_out_of_range() {
+        let src = r#"
+            function f(x) {
+                var s = x << 300;
+                return s;
+            }
+        "#;
+        validate_reports(src, 1);
+    }
+
+    #[test]
+    fn test_signed_bitwise_mismatch() {
+        // `c`'s range is a compile-time constant just above the signed
+        // 254-bit threshold (2**253), i.e. an *established* bound, not the
+        // default `[0, p - 1]` every unconstrained operand gets -- so only
+        // `c`, not the unconstrained `x`, should trip the warning.
+        let src = r#"
+            function f(x) {
+                var c = 14474011154664524427946373126085988481658748083205070504932198000989141204993;
+                var d = x & c;
+                return d;
+            }
+        "#;
+        validate_reports(src, 1);
+    }
+
+    fn validate_reports(src: &str, expected_len: usize) {
+        let mut reports = ReportCollection::new();
+        let mut cfg = parse_definition(src)
+            .unwrap()
+            .into_cfg(&Curve::default(), &mut reports)
+            .unwrap()
+            .into_ssa()
+            .unwrap();
+        assert!(reports.is_empty());
+
+        let reports = find_shift_and_bitwise_issues(&mut cfg);
+
+        assert_eq!(reports.len(), expected_len);
+    }
+}
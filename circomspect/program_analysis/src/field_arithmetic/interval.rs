@@ -0,0 +1,217 @@
+//! An interval abstract domain used to refine the field-element overflow
+//! pass (in the spirit of Frama-C's value analysis), so that it only warns
+//! about arithmetic that can actually reach or exceed the curve prime `p`,
+//! rather than every arithmetic `InfixOp`.
+
+use std::collections::HashMap;
+
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+
+use program_structure::cfg::Cfg;
+use program_structure::constants::Curve;
+use program_structure::ir::*;
+
+/// An interval `[lo, hi]` over the integers, over-approximating the set of
+/// values an expression may take before it is reduced modulo the curve
+/// prime.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Interval {
+    lo: BigInt,
+    hi: BigInt,
+}
+
+impl Interval {
+    fn constant(value: &BigInt) -> Interval {
+        Interval { lo: value.clone(), hi: value.clone() }
+    }
+
+    /// The interval used for values we know nothing about (function
+    /// parameters, signals, array accesses, and so on).
+    fn unknown(prime: &BigInt) -> Interval {
+        Interval { lo: BigInt::zero(), hi: prime - BigInt::one() }
+    }
+
+    fn add(&self, other: &Interval) -> Interval {
+        Interval { lo: &self.lo + &other.lo, hi: &self.hi + &other.hi }
+    }
+
+    fn sub(&self, other: &Interval) -> Interval {
+        Interval { lo: &self.lo - &other.hi, hi: &self.hi - &other.lo }
+    }
+
+    fn mul(&self, other: &Interval) -> Interval {
+        let products = [
+            &self.lo * &other.lo,
+            &self.lo * &other.hi,
+            &self.hi * &other.lo,
+            &self.hi * &other.hi,
+        ];
+        Interval {
+            lo: products.iter().min().unwrap().clone(),
+            hi: products.iter().max().unwrap().clone(),
+        }
+    }
+
+    /// `Pow` and the shift operators grow far too quickly to track
+    /// symbolically, so once the base (or shifted value) can exceed one, the
+    /// result is capped at the curve prime: it already overflows regardless
+    /// of the exact bound.
+    fn pow_or_shift(&self, exponent: &Interval, prime: &BigInt) -> Interval {
+        if self.hi <= BigInt::one() || exponent.hi.is_zero() {
+            return Interval { lo: BigInt::zero(), hi: self.hi.clone().max(BigInt::one()) };
+        }
+        Interval { lo: BigInt::zero(), hi: prime.clone() }
+    }
+
+    /// Least upper bound of two intervals, used to join values at `Phi`
+    /// nodes.
+    fn join(&self, other: &Interval) -> Interval {
+        Interval {
+            lo: self.lo.clone().min(other.lo.clone()),
+            hi: self.hi.clone().max(other.hi.clone()),
+        }
+    }
+
+    /// Widen `self` (the previous iteration's bound) against `next` (the
+    /// newly computed bound). A bound that has grown is jumped straight to
+    /// the extreme of the domain, so the surrounding fixpoint is guaranteed
+    /// to converge even across loop back-edges; otherwise the freshly
+    /// computed (and possibly tighter) bound is adopted.
+    fn widen(&self, next: &Interval, prime: &BigInt) -> Interval {
+        let lo = if next.lo < self.lo { BigInt::zero() } else { next.lo.clone() };
+        let hi = if next.hi > self.hi { prime.clone() } else { next.hi.clone() };
+        Interval { lo, hi }
+    }
+
+    /// True if this interval can reach or exceed the curve prime `p`, i.e.
+    /// the corresponding expression may wrap around in the field.
+    fn may_overflow(&self, prime: &BigInt) -> bool {
+        self.hi >= *prime
+    }
+}
+
+/// The result of running the interval analysis over a `Cfg`: a conservative
+/// value range for every SSA variable defined in it.
+pub struct ValueRanges {
+    ranges: HashMap<String, Interval>,
+    prime: BigInt,
+}
+
+impl ValueRanges {
+    /// Run the interval analysis to a fixpoint over `cfg`.
+    ///
+    /// Each sweep recomputes every variable's range from scratch, looking up
+    /// dependencies in the *previous* sweep's (already stable-ish) map,
+    /// rather than folding newly computed values into whatever was stored
+    /// before: joining against history instead of recomputing would only
+    /// ever let a range grow, even once its dependencies have become more
+    /// precise in a later sweep, which defeats the point of iterating.
+    /// Widening is applied across sweeps (not within one) to guarantee that
+    /// this still terminates in the presence of back-edges.
+    pub fn compute(cfg: &Cfg, curve: &Curve) -> ValueRanges {
+        let mut ranges = ValueRanges { ranges: HashMap::new(), prime: curve.prime().clone() };
+        loop {
+            let previous = ranges.ranges.clone();
+            let mut next = HashMap::new();
+            for basic_block in cfg.iter() {
+                for stmt in basic_block.iter() {
+                    ranges.visit_statement(stmt, &mut next);
+                }
+            }
+            ranges.ranges = next;
+            ranges.widen(&previous);
+            if ranges.ranges == previous {
+                break;
+            }
+        }
+        ranges
+    }
+
+    fn visit_statement(&self, stmt: &Statement, next: &mut HashMap<String, Interval>) {
+        if let Statement::Substitution { var, rhe, .. } = stmt {
+            next.insert(var.to_string(), self.range_of(rhe));
+        }
+    }
+
+    fn widen(&mut self, previous: &HashMap<String, Interval>) {
+        let prime = self.prime.clone();
+        for (name, interval) in self.ranges.iter_mut() {
+            if let Some(previous_interval) = previous.get(name) {
+                *interval = previous_interval.widen(interval, &prime);
+            }
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Interval {
+        self.ranges.get(name).cloned().unwrap_or_else(|| Interval::unknown(&self.prime))
+    }
+
+    /// The value range of `expr`, given the ranges computed for every
+    /// variable defined so far.
+    pub(crate) fn range_of(&self, expr: &Expression) -> Interval {
+        use Expression::*;
+        match expr {
+            Number(_, value) => Interval::constant(value),
+            Variable { name, .. } => self.lookup(&name.to_string()),
+            InfixOp { infix_op, lhe, rhe, .. } => {
+                self.range_of_infix(infix_op, &self.range_of(lhe), &self.range_of(rhe))
+            }
+            Phi { args, .. } => args
+                .iter()
+                .map(|(_, arg)| self.lookup(&arg.to_string()))
+                .reduce(|acc, next| acc.join(&next))
+                .unwrap_or_else(|| Interval::unknown(&self.prime)),
+            _ => Interval::unknown(&self.prime),
+        }
+    }
+
+    fn range_of_infix(
+        &self,
+        op: &ExpressionInfixOpcode,
+        lhs: &Interval,
+        rhs: &Interval,
+    ) -> Interval {
+        use ExpressionInfixOpcode::*;
+        match op {
+            Add => lhs.add(rhs),
+            Sub => lhs.sub(rhs),
+            Mul => lhs.mul(rhs),
+            Pow | ShiftL | ShiftR => lhs.pow_or_shift(rhs, &self.prime),
+            _ => Interval::unknown(&self.prime),
+        }
+    }
+
+    /// True if the `InfixOp` built from `op`, `lhe`, and `rhe` may overflow,
+    /// without requiring the caller to hold an `Expression::InfixOp` node.
+    pub(crate) fn may_overflow_infix(
+        &self,
+        op: &ExpressionInfixOpcode,
+        lhe: &Expression,
+        rhe: &Expression,
+    ) -> bool {
+        self.range_of_infix(op, &self.range_of(lhe), &self.range_of(rhe)).may_overflow(&self.prime)
+    }
+
+    /// `Some(n)` if `expr`'s computed range is the single value `n`.
+    pub(crate) fn as_constant(&self, expr: &Expression) -> Option<BigInt> {
+        let interval = self.range_of(expr);
+        (interval.lo == interval.hi).then_some(interval.lo)
+    }
+
+    /// The upper bound of `expr`'s computed range, unless that upper bound is
+    /// simply the default `p - 1` given to values the analysis knows nothing
+    /// about (an unconstrained signal, array access, or function parameter),
+    /// in which case `None` is returned. This lets callers tell an
+    /// *established* bound apart from "we just don't know" -- note that this
+    /// is a check on `hi` alone, since e.g. `Sub` on two unconstrained
+    /// operands keeps the default `hi` while still producing a novel `lo`.
+    pub(crate) fn established_upper_bound(&self, expr: &Expression) -> Option<BigInt> {
+        let interval = self.range_of(expr);
+        if interval.hi >= self.prime - BigInt::one() {
+            None
+        } else {
+            Some(interval.hi)
+        }
+    }
+}
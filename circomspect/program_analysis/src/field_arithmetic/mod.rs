@@ -1,3 +1,7 @@
+mod bitwise;
+mod interval;
+mod visit;
+
 use log::debug;
 
 use program_structure::cfg::Cfg;
@@ -5,6 +9,11 @@ use program_structure::report_code::ReportCode;
 use program_structure::report::{Report, ReportCollection};
 use program_structure::file_definition::{FileID, FileLocation};
 use program_structure::ir::*;
+use program_structure::sccp::propagate_constants;
+
+pub use bitwise::find_shift_and_bitwise_issues;
+use interval::ValueRanges;
+use visit::walk_cfg;
 
 pub struct FieldElementArithmeticWarning {
     file_id: Option<FileID>,
@@ -32,90 +41,36 @@ impl FieldElementArithmeticWarning {
 /// Field element arithmetic in Circom may overflow, which could produce
 /// unexpected results. Worst case, it may allow a malicious prover to forge
 /// proofs.
-pub fn find_field_element_arithmetic(cfg: &Cfg) -> ReportCollection {
+///
+/// This only reports an operation if an interval (value-range) analysis over
+/// the CFG shows that its result can actually reach or exceed the curve
+/// prime, so arithmetic that is provably small (e.g. bounded by a constant
+/// array size) no longer produces a false positive.
+///
+/// Constants are propagated through the CFG first (see
+/// `program_structure::sccp::propagate_constants`), so that the interval
+/// analysis sees through branches whose condition is itself a compile-time
+/// constant, rather than joining a `Phi`'s dead-branch argument into the
+/// range of a variable that can, in fact, only ever take one value.
+pub fn find_field_element_arithmetic(cfg: &mut Cfg) -> ReportCollection {
     debug!("running field element arithmetic analysis pass");
+    propagate_constants(cfg);
+    let ranges = ValueRanges::compute(cfg, cfg.curve());
     let mut reports = ReportCollection::new();
-    for basic_block in cfg.iter() {
-        for stmt in basic_block.iter() {
-            visit_statement(stmt, &mut reports);
-        }
-    }
-    debug!("{} new reports generated", reports.len());
-    reports
-}
-
-fn visit_statement(stmt: &Statement, reports: &mut ReportCollection) {
-    use Statement::*;
-    match stmt {
-        Declaration { dimensions, .. } => {
-            for size in dimensions {
-                visit_expression(size, reports);
-            }
-        }
-        LogCall { args, .. } => {
-            use LogArgument::*;
-            for arg in args {
-                if let Expr(value) = arg {
-                    visit_expression(value, reports);
-                }
-            }
-        }
-        IfThenElse { cond, .. } => visit_expression(cond, reports),
-        Substitution { rhe, .. } => visit_expression(rhe, reports),
-        Return { value, .. } => visit_expression(value, reports),
-        Assert { arg, .. } => visit_expression(arg, reports),
-        ConstraintEquality { lhe, rhe, .. } => {
-            visit_expression(lhe, reports);
-            visit_expression(rhe, reports);
+    walk_cfg(cfg, |meta, infix_op, lhe, rhe| {
+        if !may_overflow(infix_op) {
+            return true;
         }
-    }
-}
-
-fn visit_expression(expr: &Expression, reports: &mut ReportCollection) {
-    use Expression::*;
-    match expr {
-        InfixOp { meta, infix_op, .. } if may_overflow(infix_op) => {
+        if ranges.may_overflow_infix(infix_op, lhe, rhe) {
             reports.push(build_report(meta));
         }
-        InfixOp { lhe, rhe, .. } => {
-            visit_expression(lhe, reports);
-            visit_expression(rhe, reports);
-        }
-        PrefixOp { rhe, .. } => {
-            visit_expression(rhe, reports);
-        }
-        SwitchOp { cond, if_true, if_false, .. } => {
-            visit_expression(cond, reports);
-            visit_expression(if_true, reports);
-            visit_expression(if_false, reports);
-        }
-        Call { args, .. } => {
-            for arg in args {
-                visit_expression(arg, reports);
-            }
-        }
-        InlineArray { values, .. } => {
-            for value in values {
-                visit_expression(value, reports);
-            }
-        }
-        Access { access, .. } => {
-            for index in access {
-                if let AccessType::ArrayAccess(index) = index {
-                    visit_expression(index, reports);
-                }
-            }
-        }
-        Update { access, rhe, .. } => {
-            for index in access {
-                if let AccessType::ArrayAccess(index) = index {
-                    visit_expression(index, reports);
-                }
-            }
-            visit_expression(rhe, reports);
-        }
-        Number(_, _) | Variable { .. } | Phi { .. } => (),
-    }
+        // Matches the original, pre-range-analysis traversal: an operator
+        // eligible for the overflow check does not recurse into its own
+        // operands.
+        false
+    });
+    debug!("{} new reports generated", reports.len());
+    reports
 }
 
 fn is_arithmetic_infix_op(op: &ExpressionInfixOpcode) -> bool {
@@ -156,10 +111,33 @@ mod tests {
         validate_reports(src, 2);
     }
 
+    #[test]
+    fn test_sccp_improves_precision() {
+        // Without constant propagation, `x`'s range at the join point would
+        // be the join of both branches ([2, 2] and the huge else-branch
+        // constant), making `x * x` look like it could overflow. Since the
+        // condition is a compile-time constant, SCCP should resolve `x` to
+        // exactly 2 before the interval analysis ever runs, so no warning is
+        // produced.
+        let src = r#"
+            function f() {
+                var x;
+                if (1) {
+                    x = 2;
+                } else {
+                    x = 21888242871839275222246405745257275088548364400416034343698204186575743488407;
+                }
+                var y = x * x;
+                return y;
+            }
+        "#;
+        validate_reports(src, 0);
+    }
+
     fn validate_reports(src: &str, expected_len: usize) {
         // Build CFG.
         let mut reports = ReportCollection::new();
-        let cfg = parse_definition(src)
+        let mut cfg = parse_definition(src)
             .unwrap()
             .into_cfg(&Curve::default(), &mut reports)
             .unwrap()
@@ -168,7 +146,7 @@ mod tests {
         assert!(reports.is_empty());
 
         // Generate report collection.
-        let reports = find_field_element_arithmetic(&cfg);
+        let reports = find_field_element_arithmetic(&mut cfg);
 
         assert_eq!(reports.len(), expected_len);
     }
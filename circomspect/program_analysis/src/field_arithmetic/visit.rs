@@ -0,0 +1,98 @@
+//! The statement/expression traversal shared by the field-arithmetic lint
+//! passes (`find_field_element_arithmetic`, `find_shift_and_bitwise_issues`):
+//! both only care about `InfixOp` nodes, and otherwise just walk the same
+//! `Statement`/`Expression` shape, so the walk itself lives here once.
+
+use program_structure::cfg::Cfg;
+use program_structure::ir::*;
+
+/// Walk every statement in `cfg`, calling `visit_infix` for each `InfixOp`
+/// node encountered. `visit_infix` returns whether the walk should keep
+/// recursing into that operator's own operands.
+pub(crate) fn walk_cfg<'a>(
+    cfg: &'a Cfg,
+    mut visit_infix: impl FnMut(&'a Meta, &'a ExpressionInfixOpcode, &'a Expression, &'a Expression) -> bool,
+) {
+    for basic_block in cfg.iter() {
+        for stmt in basic_block.iter() {
+            walk_statement(stmt, &mut visit_infix);
+        }
+    }
+}
+
+fn walk_statement<'a>(
+    stmt: &'a Statement,
+    visit_infix: &mut impl FnMut(&'a Meta, &'a ExpressionInfixOpcode, &'a Expression, &'a Expression) -> bool,
+) {
+    use Statement::*;
+    match stmt {
+        Declaration { dimensions, .. } => {
+            for size in dimensions {
+                walk_expression(size, visit_infix);
+            }
+        }
+        LogCall { args, .. } => {
+            use LogArgument::*;
+            for arg in args {
+                if let Expr(value) = arg {
+                    walk_expression(value, visit_infix);
+                }
+            }
+        }
+        IfThenElse { cond, .. } => walk_expression(cond, visit_infix),
+        Substitution { rhe, .. } => walk_expression(rhe, visit_infix),
+        Return { value, .. } => walk_expression(value, visit_infix),
+        Assert { arg, .. } => walk_expression(arg, visit_infix),
+        ConstraintEquality { lhe, rhe, .. } => {
+            walk_expression(lhe, visit_infix);
+            walk_expression(rhe, visit_infix);
+        }
+    }
+}
+
+fn walk_expression<'a>(
+    expr: &'a Expression,
+    visit_infix: &mut impl FnMut(&'a Meta, &'a ExpressionInfixOpcode, &'a Expression, &'a Expression) -> bool,
+) {
+    use Expression::*;
+    match expr {
+        InfixOp { meta, infix_op, lhe, rhe, .. } => {
+            if visit_infix(meta, infix_op, lhe, rhe) {
+                walk_expression(lhe, visit_infix);
+                walk_expression(rhe, visit_infix);
+            }
+        }
+        PrefixOp { rhe, .. } => walk_expression(rhe, visit_infix),
+        SwitchOp { cond, if_true, if_false, .. } => {
+            walk_expression(cond, visit_infix);
+            walk_expression(if_true, visit_infix);
+            walk_expression(if_false, visit_infix);
+        }
+        Call { args, .. } => {
+            for arg in args {
+                walk_expression(arg, visit_infix);
+            }
+        }
+        InlineArray { values, .. } => {
+            for value in values {
+                walk_expression(value, visit_infix);
+            }
+        }
+        Access { access, .. } => {
+            for index in access {
+                if let AccessType::ArrayAccess(index) = index {
+                    walk_expression(index, visit_infix);
+                }
+            }
+        }
+        Update { access, rhe, .. } => {
+            for index in access {
+                if let AccessType::ArrayAccess(index) = index {
+                    walk_expression(index, visit_infix);
+                }
+            }
+            walk_expression(rhe, visit_infix);
+        }
+        Number(_, _) | Variable { .. } | Phi { .. } => (),
+    }
+}
@@ -0,0 +1,15 @@
+/// Identifies the category of a diagnostic `Report`, so that tooling (CLI
+/// flags, editor integrations) can filter or configure reports by kind
+/// independently of their severity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReportCode {
+    FieldElementArithmetic,
+    /// A `ShiftL`/`ShiftR` shift amount is negative, not smaller than the
+    /// field's bit-width, or isn't a compile-time constant at all.
+    ShiftAmountOutOfRange,
+    /// A `BitAnd`/`BitOr`/`BitXor` operand's value range extends into the
+    /// upper half of the field, where Circom's signed two's-complement
+    /// interpretation of field elements diverges from plain integer
+    /// intuition.
+    SignedBitwiseMismatch,
+}
@@ -0,0 +1,368 @@
+//! Sparse conditional constant propagation (SCCP) over the SSA `Cfg`.
+//!
+//! This is the classical SSA mid-end optimization: a lattice over SSA
+//! variables combined with an executable-block fixpoint, so that
+//! expressions built entirely out of compile-time constants (including
+//! through branches that are provably always taken one way) get folded to
+//! `Expression::Number`. Running this before a lint pass such as
+//! `field_arithmetic`'s overflow analysis lets that pass's interval
+//! analysis see through things like constant array indices instead of
+//! falling back to the full `[0, p - 1]` range.
+
+use std::collections::{HashMap, HashSet};
+
+use log::debug;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::cfg::Cfg;
+use crate::ir::*;
+
+/// The SCCP lattice for a single SSA variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum LatticeValue {
+    /// Not yet known to be anything (lattice top).
+    Unknown,
+    /// Proven to always evaluate to this constant.
+    Constant(BigInt),
+    /// Proven to take more than one value (lattice bottom).
+    Overdefined,
+}
+
+impl LatticeValue {
+    fn meet(&self, other: &LatticeValue) -> LatticeValue {
+        use LatticeValue::*;
+        match (self, other) {
+            (Unknown, value) | (value, Unknown) => value.clone(),
+            (Constant(x), Constant(y)) if x == y => Constant(x.clone()),
+            _ => Overdefined,
+        }
+    }
+}
+
+/// Run SCCP to a fixpoint over `cfg` and rewrite it in place, replacing
+/// variables and subexpressions proven constant with `Expression::Number`.
+/// Returns whether anything was rewritten.
+pub fn propagate_constants(cfg: &mut Cfg) -> bool {
+    debug!("running sparse conditional constant propagation pass");
+    let prime = cfg.curve().prime().clone();
+
+    let (values, executable_edges) = analyze(cfg, &prime);
+    let executable_blocks = executable_blocks(&executable_edges);
+    let changed = rewrite(cfg, &values, &executable_blocks);
+
+    debug!("sccp pass {}", if changed { "rewrote the cfg" } else { "made no changes" });
+    changed
+}
+
+/// The block `current` is executable if it's the entry block, or if some
+/// executable edge leads into it.
+fn executable_blocks(executable_edges: &HashSet<(usize, usize)>) -> HashSet<usize> {
+    let mut blocks = HashSet::new();
+    blocks.insert(0);
+    blocks.extend(executable_edges.iter().map(|(_, to)| *to));
+    blocks
+}
+
+/// Compute, for every SSA variable, the constant it always evaluates to (if
+/// any), together with the set of CFG edges that are reachable given the
+/// branches that can be resolved at compile time.
+fn analyze(cfg: &Cfg, prime: &BigInt) -> (HashMap<String, LatticeValue>, HashSet<(usize, usize)>) {
+    let blocks: Vec<_> = cfg.iter().collect();
+
+    let mut values: HashMap<String, LatticeValue> = HashMap::new();
+    let mut executable_edges: HashSet<(usize, usize)> = HashSet::new();
+
+    loop {
+        let previous_values = values.clone();
+        let previous_edges = executable_edges.clone();
+        let executable = executable_blocks(&executable_edges);
+
+        for (index, block) in blocks.iter().enumerate() {
+            if !executable.contains(&index) {
+                continue;
+            }
+            for stmt in block.iter() {
+                visit_statement(index, stmt, &mut values, &mut executable_edges, prime);
+            }
+        }
+
+        if values == previous_values && executable_edges == previous_edges {
+            break;
+        }
+    }
+    (values, executable_edges)
+}
+
+fn visit_statement(
+    current_index: usize,
+    stmt: &Statement,
+    values: &mut HashMap<String, LatticeValue>,
+    executable_edges: &mut HashSet<(usize, usize)>,
+    prime: &BigInt,
+) {
+    use Statement::*;
+    match stmt {
+        Substitution { var, rhe, .. } => {
+            let computed = evaluate(current_index, rhe, values, executable_edges, prime);
+            let key = var.to_string();
+            let joined = match values.get(&key) {
+                Some(previous) => previous.meet(&computed),
+                None => computed,
+            };
+            values.insert(key, joined);
+        }
+        IfThenElse { cond, if_true, if_false, .. } => {
+            match evaluate(current_index, cond, values, executable_edges, prime) {
+                LatticeValue::Constant(value) if value.is_zero() => {
+                    executable_edges.insert((current_index, *if_false));
+                }
+                LatticeValue::Constant(_) => {
+                    executable_edges.insert((current_index, *if_true));
+                }
+                // The condition isn't known to be a single constant yet (or
+                // ever will be): conservatively treat both branches as
+                // reachable.
+                LatticeValue::Unknown | LatticeValue::Overdefined => {
+                    executable_edges.insert((current_index, *if_true));
+                    executable_edges.insert((current_index, *if_false));
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Evaluate `expr` in the constant-propagation lattice, given the values
+/// computed for every variable so far. `current_index` is the index of the
+/// block `expr` appears in, needed to resolve `Phi` arguments against the
+/// edges proven executable so far.
+fn evaluate(
+    current_index: usize,
+    expr: &Expression,
+    values: &HashMap<String, LatticeValue>,
+    executable_edges: &HashSet<(usize, usize)>,
+    prime: &BigInt,
+) -> LatticeValue {
+    use Expression::*;
+    match expr {
+        Number(_, value) => LatticeValue::Constant(value.mod_floor(prime)),
+        Variable { name, .. } => values.get(&name.to_string()).cloned().unwrap_or(LatticeValue::Unknown),
+        InfixOp { infix_op, lhe, rhe, .. } => {
+            let lhs = evaluate(current_index, lhe, values, executable_edges, prime);
+            let rhs = evaluate(current_index, rhe, values, executable_edges, prime);
+            match (lhs, rhs) {
+                (LatticeValue::Constant(l), LatticeValue::Constant(r)) => {
+                    match evaluate_infix(infix_op, &l, &r, prime) {
+                        Some(value) => LatticeValue::Constant(value),
+                        None => LatticeValue::Overdefined,
+                    }
+                }
+                (LatticeValue::Overdefined, _) | (_, LatticeValue::Overdefined) => {
+                    LatticeValue::Overdefined
+                }
+                _ => LatticeValue::Unknown,
+            }
+        }
+        PrefixOp { prefix_op, rhe, .. } => {
+            match evaluate(current_index, rhe, values, executable_edges, prime) {
+                LatticeValue::Constant(value) => match evaluate_prefix(prefix_op, &value, prime) {
+                    Some(value) => LatticeValue::Constant(value),
+                    None => LatticeValue::Overdefined,
+                },
+                other => other,
+            }
+        }
+        // Only meet over the phi arguments whose incoming edge has actually
+        // been proven executable; an argument defined solely on a
+        // statically-dead branch must not pull the result down to
+        // `Overdefined`.
+        Phi { args, .. } => args
+            .iter()
+            .filter(|(predecessor, _)| executable_edges.contains(&(*predecessor, current_index)))
+            .map(|(_, arg)| values.get(&arg.to_string()).cloned().unwrap_or(LatticeValue::Unknown))
+            .reduce(|acc, next| acc.meet(&next))
+            .unwrap_or(LatticeValue::Unknown),
+        _ => LatticeValue::Overdefined,
+    }
+}
+
+/// A shift by more bits than this can't possibly produce anything other than
+/// `0` (or, for `ShiftR` on a value already reduced into the field, something
+/// indistinguishable from it) once reduced modulo the curve prime, so there's
+/// no need to materialize the shift itself -- which matters because a
+/// constant shift amount is attacker-controlled input to this pass and
+/// `BigInt`'s shift operators will happily try to allocate a multi-gigabit
+/// result for an amount like `4_000_000_000`.
+const MAX_FOLDABLE_SHIFT: u32 = 4096;
+
+fn evaluate_infix(op: &ExpressionInfixOpcode, l: &BigInt, r: &BigInt, prime: &BigInt) -> Option<BigInt> {
+    use ExpressionInfixOpcode::*;
+    let as_bool = |b: bool| BigInt::from(b as u8);
+    let shift_amount = |r: &BigInt| r.to_u32().filter(|amount| *amount <= MAX_FOLDABLE_SHIFT);
+    let value = match op {
+        Add => l + r,
+        Sub => l - r,
+        Mul => l * r,
+        IntDiv if !r.is_zero() => l / r,
+        Mod if !r.is_zero() => l.mod_floor(r),
+        BitAnd => l & r,
+        BitOr => l | r,
+        BitXor => l ^ r,
+        ShiftL => l << shift_amount(r)?,
+        ShiftR => l >> shift_amount(r)?,
+        Lesser => as_bool(l < r),
+        Greater => as_bool(l > r),
+        LesserEq => as_bool(l <= r),
+        GreaterEq => as_bool(l >= r),
+        Eq => as_bool(l == r),
+        NotEq => as_bool(l != r),
+        BoolAnd => as_bool(!l.is_zero() && !r.is_zero()),
+        BoolOr => as_bool(!l.is_zero() || !r.is_zero()),
+        _ => return None,
+    };
+    Some(value.mod_floor(prime))
+}
+
+fn evaluate_prefix(op: &ExpressionPrefixOpcode, value: &BigInt, prime: &BigInt) -> Option<BigInt> {
+    use ExpressionPrefixOpcode::*;
+    let result = match op {
+        Sub => -value,
+        BoolNot => BigInt::from(value.is_zero() as u8),
+        _ => return None,
+    };
+    Some(result.mod_floor(prime))
+}
+
+/// Rewrite every variable reference known to be constant into a plain
+/// `Expression::Number`. Returns whether anything changed.
+fn rewrite(cfg: &mut Cfg, values: &HashMap<String, LatticeValue>, executable: &HashSet<usize>) -> bool {
+    let mut changed = false;
+    for (index, block) in cfg.iter_mut().enumerate() {
+        if !executable.contains(&index) {
+            continue;
+        }
+        for stmt in block.iter_mut() {
+            changed |= rewrite_statement(stmt, values);
+        }
+    }
+    changed
+}
+
+fn rewrite_statement(stmt: &mut Statement, values: &HashMap<String, LatticeValue>) -> bool {
+    use Statement::*;
+    match stmt {
+        Declaration { dimensions, .. } => {
+            dimensions.iter_mut().fold(false, |acc, size| acc | rewrite_expression(size, values))
+        }
+        LogCall { args, .. } => args.iter_mut().fold(false, |acc, arg| {
+            if let LogArgument::Expr(value) = arg {
+                acc | rewrite_expression(value, values)
+            } else {
+                acc
+            }
+        }),
+        IfThenElse { cond, .. } => rewrite_expression(cond, values),
+        Substitution { rhe, .. } => rewrite_expression(rhe, values),
+        Return { value, .. } => rewrite_expression(value, values),
+        Assert { arg, .. } => rewrite_expression(arg, values),
+        ConstraintEquality { lhe, rhe, .. } => {
+            rewrite_expression(lhe, values) | rewrite_expression(rhe, values)
+        }
+    }
+}
+
+fn rewrite_expression(expr: &mut Expression, values: &HashMap<String, LatticeValue>) -> bool {
+    use Expression::*;
+    if let Variable { meta, name, .. } = expr {
+        if let Some(LatticeValue::Constant(value)) = values.get(&name.to_string()) {
+            *expr = Number(meta.clone(), value.clone());
+            return true;
+        }
+    }
+    match expr {
+        InfixOp { lhe, rhe, .. } => {
+            rewrite_expression(lhe, values) | rewrite_expression(rhe, values)
+        }
+        PrefixOp { rhe, .. } => rewrite_expression(rhe, values),
+        SwitchOp { cond, if_true, if_false, .. } => {
+            rewrite_expression(cond, values)
+                | rewrite_expression(if_true, values)
+                | rewrite_expression(if_false, values)
+        }
+        Call { args, .. } => args.iter_mut().fold(false, |acc, arg| acc | rewrite_expression(arg, values)),
+        InlineArray { values: elements, .. } => {
+            elements.iter_mut().fold(false, |acc, value| acc | rewrite_expression(value, values))
+        }
+        Access { access, .. } => access.iter_mut().fold(false, |acc, index| {
+            if let AccessType::ArrayAccess(index) = index {
+                acc | rewrite_expression(index, values)
+            } else {
+                acc
+            }
+        }),
+        Update { access, rhe, .. } => {
+            let changed = access.iter_mut().fold(false, |acc, index| {
+                if let AccessType::ArrayAccess(index) = index {
+                    acc | rewrite_expression(index, values)
+                } else {
+                    acc
+                }
+            });
+            changed | rewrite_expression(rhe, values)
+        }
+        Number(_, _) | Variable { .. } | Phi { .. } => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parser::parse_definition;
+
+    use crate::{cfg::IntoCfg, constants::Curve, report::ReportCollection};
+
+    use super::*;
+
+    #[test]
+    fn test_fold_across_loop_back_edge() {
+        // `x` is never reassigned in the loop body, so its phi at the loop
+        // header merges the entry edge and the back edge, both carrying the
+        // same constant -- the fold must follow that back edge to see it,
+        // not just the entry edge, for `y = x + 1` to resolve to `6` on
+        // every sweep regardless of how many times the (unknown-bound) loop
+        // actually runs.
+        let src = r#"
+            function f(n) {
+                var x = 5;
+                var i = 0;
+                var y = 0;
+                while (i < n) {
+                    y = x + 1;
+                    i = i + 1;
+                }
+                return y;
+            }
+        "#;
+        let mut reports = ReportCollection::new();
+        let mut cfg = parse_definition(src)
+            .unwrap()
+            .into_cfg(&Curve::default(), &mut reports)
+            .unwrap()
+            .into_ssa()
+            .unwrap();
+        assert!(reports.is_empty());
+
+        propagate_constants(&mut cfg);
+
+        let folded_to_six = cfg.iter().any(|block| {
+            block.iter().any(|stmt| match stmt {
+                Statement::Substitution { var, rhe: Expression::Number(_, value), .. } => {
+                    var.to_string().contains('y') && *value == BigInt::from(6)
+                }
+                _ => false,
+            })
+        });
+        assert!(folded_to_six, "`y = x + 1` should fold to the constant 6 inside the loop body");
+    }
+}
@@ -0,0 +1,51 @@
+//! Liveness analysis used to prune the insertion of dead phi statements
+//! during SSA construction (see `insert_phi_statements` in the parent
+//! module).
+
+use std::collections::HashSet;
+
+use super::traits::*;
+
+/// Compute the live-in set for every basic block in `basic_blocks`, via the
+/// standard backward dataflow fixpoint:
+///
+/// ```text
+/// live_out[b] = \bigcup_{s \in succ(b)} live_in[s]
+/// live_in[b]  = use[b] \cup (live_out[b] \ def[b])
+/// ```
+///
+/// where `use[b]` and `def[b]` are the variables read and written in `b`
+/// respectively.
+pub fn compute_live_in<Cfg: SSAConfig>(
+    basic_blocks: &[Cfg::BasicBlock],
+) -> Vec<HashSet<Cfg::Variable>> {
+    let mut live_in: Vec<HashSet<Cfg::Variable>> = vec![HashSet::new(); basic_blocks.len()];
+    let mut live_out: Vec<HashSet<Cfg::Variable>> = vec![HashSet::new(); basic_blocks.len()];
+    loop {
+        let mut changed = false;
+        // Process blocks in reverse order; this isn't required for
+        // correctness but tends to reach the fixpoint in fewer iterations
+        // since blocks usually appear before their successors.
+        for index in (0..basic_blocks.len()).rev() {
+            let block = &basic_blocks[index];
+
+            let mut new_live_out = HashSet::new();
+            for successor in block.successors() {
+                new_live_out.extend(live_in[successor].iter().cloned());
+            }
+
+            let mut new_live_in = block.variables_read().clone();
+            new_live_in.extend(new_live_out.difference(block.variables_written()).cloned());
+
+            if new_live_in != live_in[index] || new_live_out != live_out[index] {
+                changed = true;
+                live_in[index] = new_live_in;
+                live_out[index] = new_live_out;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    live_in
+}
@@ -2,21 +2,28 @@
 //! form.
 pub mod dominator_tree;
 pub mod errors;
+pub mod liveness;
 pub mod traits;
 
 use log::trace;
 
 use dominator_tree::DominatorTree;
 use errors::SSAResult;
+use liveness::compute_live_in;
 use traits::*;
 
-/// Insert a dummy phi statement in block `j`, for each variable written in block
-/// `i`, if `j` is in the dominance frontier of `i`.
+/// Insert a dummy phi statement in block `j`, for each variable written in
+/// block `i`, if `j` is in the dominance frontier of `i` and the variable is
+/// live at `j` (pruned SSA). Placing phis only where the variable is live
+/// avoids filling the dominance frontier with dead phi functions that the
+/// downstream analyses would otherwise have to walk through.
 pub fn insert_phi_statements<Cfg: SSAConfig>(
     basic_blocks: &mut [Cfg::BasicBlock],
     dominator_tree: &DominatorTree<Cfg::BasicBlock>,
     env: &mut Cfg::Environment,
 ) {
+    let live_in = compute_live_in::<Cfg>(basic_blocks);
+
     // Insert phi statements at the dominance frontier of each block.
     let mut work_list: Vec<Index> = (0..basic_blocks.len()).collect();
     while let Some(current_index) = work_list.pop() {
@@ -35,6 +42,11 @@ pub fn insert_phi_statements<Cfg: SSAConfig>(
         for frontier_index in dominator_tree.get_dominance_frontier(current_index) {
             let frontier_block = &mut basic_blocks[frontier_index];
             for var in &variables_written {
+                if !live_in[frontier_index].contains(var) {
+                    // The variable is dead at this join point: a phi here
+                    // would never be read, so skip it.
+                    continue;
+                }
                 if !frontier_block.has_phi_statement(var) {
                     // If a phi statement was added to the block we need to
                     // re-add the block to the work list.
@@ -89,3 +101,54 @@ fn insert_ssa_variables_impl<Cfg: SSAConfig>(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use parser::parse_definition;
+
+    use crate::{cfg::IntoCfg, constants::Curve, ir::*, report::ReportCollection};
+
+    #[test]
+    fn test_prunes_dead_phi_but_keeps_live_phi() {
+        // `live` is read after the branch joins (via `return`), so pruned
+        // SSA must insert a phi for it at the join block. `dead` is written
+        // in both branches but never read again, so inserting a phi for it
+        // would just be dead code for every downstream analysis to walk
+        // through.
+        let src = r#"
+            function f(cond) {
+                var live = 0;
+                var dead = 0;
+                if (cond) {
+                    live = 1;
+                    dead = 1;
+                } else {
+                    live = 2;
+                    dead = 2;
+                }
+                return live;
+            }
+        "#;
+        let mut reports = ReportCollection::new();
+        let cfg = parse_definition(src)
+            .unwrap()
+            .into_cfg(&Curve::default(), &mut reports)
+            .unwrap()
+            .into_ssa()
+            .unwrap();
+        assert!(reports.is_empty());
+
+        let has_phi_for = |name: &str| {
+            cfg.iter().any(|block| {
+                block.iter().any(|stmt| matches!(
+                    stmt,
+                    Statement::Substitution { var, rhe: Expression::Phi { .. }, .. }
+                        if var.to_string().contains(name)
+                ))
+            })
+        };
+
+        assert!(has_phi_for("live"), "a live variable read after the join needs a phi");
+        assert!(!has_phi_for("dead"), "a dead variable should not get a pruned-SSA phi");
+    }
+}